@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::iterator::{SourceHeap, TraitIterator};
+use crate::key::{KeyBytes, KeyKind, KeySlice, KeyTimestamp};
+
+/// A logical-compaction pass that merges several overlapping `disk_table` runs
+/// into a single sorted output while garbage-collecting versions that no live
+/// reader can distinguish any longer.
+///
+/// The `watermark` is the oldest timestamp any live reader can still pin. For
+/// every user key the compactor keeps all versions newer than the watermark (so
+/// open snapshots still see the history they pinned) and at most the single
+/// newest version at-or-below it; a surviving tombstone is dropped outright when
+/// nothing remains visible above the watermark. Passing
+/// [`TIMESTAMP_RANGE_BEGIN`](crate::key::TIMESTAMP_RANGE_BEGIN) as the watermark
+/// preserves every version, disabling GC.
+///
+/// Each input iterator must yield its entries in the database's key order and
+/// signal exhaustion with an empty key.
+pub struct Compactor<I> {
+    heap: SourceHeap<I>,
+    watermark: KeyTimestamp,
+    pending: VecDeque<(KeyBytes, Bytes)>,
+}
+
+impl<I> Compactor<I>
+where
+    I: for<'a> TraitIterator<KeyType<'a> = KeySlice<'a>> + 'static,
+{
+    /// Builds a compactor over `sources`, collapsing every version that is no
+    /// longer distinguishable below `watermark`.
+    pub fn new(sources: Vec<I>, watermark: KeyTimestamp) -> Self {
+        Compactor {
+            heap: SourceHeap::new(sources),
+            watermark,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Pulls the next GC'd entry, or `None` once every source is drained.
+    pub fn next(&mut self) -> Result<Option<(KeyBytes, Bytes)>> {
+        // A group can lose every version to GC, so keep pulling groups until
+        // something survives or the heap is empty.
+        while self.pending.is_empty() {
+            if !self.fill_group()? {
+                break;
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    /// Drains every version of the next user key off the heap, applies the GC
+    /// rules, and queues the survivors in ascending key order. Returns `false`
+    /// once the heap is empty.
+    fn fill_group(&mut self) -> Result<bool> {
+        let Some(first) = self.heap.pop()? else {
+            return Ok(false);
+        };
+        let user_key = first.user_key().to_vec();
+
+        let mut group = vec![first];
+        while self
+            .heap
+            .peek()
+            .is_some_and(|entry| entry.user_key() == user_key.as_slice())
+        {
+            group.push(self.heap.pop()?.unwrap());
+        }
+        // Each source hands over its own versions in its own order, so sort
+        // `group` newest-first (timestamp descending, newer source first).
+        group.sort();
+
+        let mut kept = Vec::new();
+        let mut kept_below = false;
+        let mut above_survives = false;
+        let mut last_ts: Option<KeyTimestamp> = None;
+        for entry in group {
+            let ts = entry.timestamp();
+            if last_ts == Some(ts) {
+                // A stale duplicate of the same version from an older run.
+                continue;
+            }
+            last_ts = Some(ts);
+            if ts > self.watermark {
+                above_survives = true;
+                kept.push(entry);
+            } else if !kept_below {
+                kept_below = true;
+                if matches!(entry.kind(), KeyKind::Delete) && !above_survives {
+                    // Newest version is a tombstone and nothing shadows it from
+                    // above the watermark, so the key can disappear entirely.
+                    continue;
+                }
+                kept.push(entry);
+            }
+        }
+
+        for entry in kept.into_iter().rev() {
+            self.pending.push_back((entry.key, entry.value));
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{entry, VecIter};
+
+    fn collect<I>(mut compactor: Compactor<I>) -> Vec<(Vec<u8>, KeyTimestamp, KeyKind, Vec<u8>)>
+    where
+        I: for<'a> TraitIterator<KeyType<'a> = KeySlice<'a>> + 'static,
+    {
+        let mut out = Vec::new();
+        while let Some((key, value)) = compactor.next().unwrap() {
+            out.push((key.key_ref().to_vec(), key.timestamp(), key.kind(), value.to_vec()));
+        }
+        out
+    }
+
+    #[test]
+    fn drops_below_watermark_tombstone() {
+        // Both versions are at or below the watermark, so they collapse to one;
+        // the newest is a tombstone with nothing above it, so the key vanishes.
+        let source = VecIter::new(vec![
+            entry("a", 1, KeyKind::Set, "v1"),
+            entry("a", 2, KeyKind::Delete, ""),
+        ]);
+        let out = collect(Compactor::new(vec![source], 5));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn preserves_above_watermark_versions() {
+        // The Set is below the watermark (collapsed to the newest), but the
+        // tombstone is above it and must survive so pinned snapshots still see
+        // the delete.
+        let source = VecIter::new(vec![
+            entry("a", 1, KeyKind::Set, "v1"),
+            entry("a", 10, KeyKind::Delete, ""),
+        ]);
+        let out = collect(Compactor::new(vec![source], 5));
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].1, 1);
+        assert!(matches!(out[0].2, KeyKind::Set));
+        assert_eq!(out[1].1, 10);
+        assert!(matches!(out[1].2, KeyKind::Delete));
+    }
+
+    #[test]
+    fn collapses_below_watermark_to_newest_value() {
+        let source = VecIter::new(vec![
+            entry("k", 1, KeyKind::Set, "old"),
+            entry("k", 2, KeyKind::Set, "new"),
+        ]);
+        let out = collect(Compactor::new(vec![source], 5));
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].1, 2);
+        assert_eq!(out[0].3, b"new");
+    }
+}
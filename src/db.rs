@@ -1,39 +1,162 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
 use anyhow::Result;
 use bytes::Bytes;
 
 use crate::batch::{Batch, BatchType};
-use crate::transaction::Transaction;
+use crate::key::{KeyTimestamp, TIMESTAMP_RANGE_BEGIN};
+use crate::transaction::{Transaction, TransactionHandle, TransactionResult};
 
 pub struct DB {
-
+    inner: Arc<DbInner>,
 }
 
 impl DB {
     pub fn open() -> Self {
-        unimplemented!()
+        DB {
+            inner: Arc::new(DbInner::new()),
+        }
     }
 
-    pub fn apply_batch<const T: BatchType>(&self, _batch: Batch<T>) -> Result<()> {
-        unimplemented!()
+    pub fn apply_batch<const T: BatchType>(&self, batch: Batch<T>) -> Result<()> {
+        if T == BatchType::Write {
+            self.inner.apply_writes(&batch.items);
+        }
+        Ok(())
     }
-    
-    pub fn transaction(&self) -> TransactionHandle {
-        unimplemented!()
+
+    /// Prepares a transaction whose body runs lazily when
+    /// [`TransactionHandle::execute`] is called.
+    pub fn transaction<F>(&self, body: F) -> TransactionHandle<F>
+    where
+        F: FnOnce(&mut Transaction) -> Result<()>,
+    {
+        TransactionHandle::new(self.inner.clone(), body)
     }
-    
-    pub fn get(&self, _key: Bytes) {
-        unimplemented!()
+
+    pub fn get(&self, key: Bytes) -> Option<Bytes> {
+        self.inner.read(&key, self.inner.read_timestamp())
     }
 
     pub fn insert(&self, key: Bytes, value: Bytes) -> Result<()> {
-        let mut batch  = Batch::write();
+        let mut batch = Batch::write();
         batch.insert(key, value);
         self.apply_batch(batch)
     }
 
     pub fn remove(&self, key: Bytes) -> Result<()> {
-        let mut batch  = Batch::write();
+        let mut batch = Batch::write();
         batch.remove(key);
         self.apply_batch(batch)
     }
-}
\ No newline at end of file
+}
+
+/// A committed version of a user key: the sequence it was written at and its
+/// value, where `None` is a tombstone.
+type Version = (KeyTimestamp, Option<Bytes>);
+
+/// Shared, reference-counted database state. Storage is a single in-memory
+/// multi-version map until the memtable and `disk_table` layers are wired in;
+/// it already carries the MVCC sequence oracle and commit lock that
+/// transactions rely on.
+pub(crate) struct DbInner {
+    /// Monotonic sequence oracle; its current value is the latest committed
+    /// sequence number and the default read timestamp.
+    sequence: AtomicU64,
+    /// Serializes commit validation and application across transactions.
+    commit_lock: Mutex<()>,
+    /// User key -> versions in ascending sequence order.
+    store: Mutex<BTreeMap<Bytes, Vec<Version>>>,
+}
+
+impl DbInner {
+    fn new() -> Self {
+        DbInner {
+            sequence: AtomicU64::new(TIMESTAMP_RANGE_BEGIN),
+            commit_lock: Mutex::new(()),
+            store: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// The latest committed sequence number, used as a snapshot's read timestamp.
+    pub(crate) fn read_timestamp(&self) -> KeyTimestamp {
+        self.sequence.load(Ordering::Acquire)
+    }
+
+    /// Acquires the commit lock so a synchronous transaction can serialize its
+    /// whole body against other committers.
+    pub(crate) fn lock_commit(&self) -> MutexGuard<'_, ()> {
+        self.commit_lock.lock().unwrap()
+    }
+
+    /// Reads the newest version of `key` visible at `read_timestamp`.
+    pub(crate) fn read(&self, key: &Bytes, read_timestamp: KeyTimestamp) -> Option<Bytes> {
+        let store = self.store.lock().unwrap();
+        let versions = store.get(key)?;
+        versions
+            .iter()
+            .rev()
+            .find(|(ts, _)| *ts <= read_timestamp)
+            .and_then(|(_, value)| value.clone())
+    }
+
+    /// Validates the optimistic read set under the commit lock and, if no read
+    /// was overwritten by a newer commit, applies the buffered writes at a fresh
+    /// sequence number.
+    pub(crate) fn validate_and_commit(
+        &self,
+        reads: &BTreeSet<Bytes>,
+        writes: &BTreeMap<Bytes, Option<Bytes>>,
+        read_timestamp: KeyTimestamp,
+    ) -> TransactionResult {
+        let _guard = self.commit_lock.lock().unwrap();
+        let mut store = self.store.lock().unwrap();
+        for key in reads {
+            if let Some(versions) = store.get(key) {
+                if versions.last().is_some_and(|(ts, _)| *ts > read_timestamp) {
+                    return TransactionResult::Conflict;
+                }
+            }
+        }
+        let sequence = self.assign_sequence();
+        Self::apply_into(&mut store, writes, sequence);
+        TransactionResult::Committed(sequence)
+    }
+
+    /// Applies buffered writes at a fresh sequence. The caller already holds the
+    /// commit lock (synchronous transactions).
+    pub(crate) fn commit_locked(&self, writes: &BTreeMap<Bytes, Option<Bytes>>) -> KeyTimestamp {
+        let mut store = self.store.lock().unwrap();
+        let sequence = self.assign_sequence();
+        Self::apply_into(&mut store, writes, sequence);
+        sequence
+    }
+
+    /// Applies a non-transactional batch at a single fresh sequence number.
+    fn apply_writes(&self, items: &BTreeMap<Bytes, Option<Bytes>>) {
+        let _guard = self.commit_lock.lock().unwrap();
+        let mut store = self.store.lock().unwrap();
+        let sequence = self.assign_sequence();
+        Self::apply_into(&mut store, items, sequence);
+    }
+
+    /// Hands out the next sequence number and advances the oracle.
+    fn assign_sequence(&self) -> KeyTimestamp {
+        self.sequence.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    fn apply_into(
+        store: &mut BTreeMap<Bytes, Vec<Version>>,
+        writes: &BTreeMap<Bytes, Option<Bytes>>,
+        sequence: KeyTimestamp,
+    ) {
+        for (key, value) in writes {
+            store
+                .entry(key.clone())
+                .or_default()
+                .push((sequence, value.clone()));
+        }
+    }
+}
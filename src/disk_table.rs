@@ -0,0 +1,384 @@
+use std::path::Path;
+
+use anyhow::{anyhow, ensure, Result};
+use bytes::Bytes;
+use memmap2::Mmap;
+
+use crate::iterator::TraitIterator;
+use crate::key::{KeySlice, KeyKind, KeyTrailer};
+
+/// Identifies a finished SSTable and guards against reading a foreign or
+/// truncated file.
+const MAGIC: u64 = 0x424f554c_44455231; // "BOULDER1"
+/// On-disk format the writer emitted. Bumped for layout changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Footer size in bytes: index offset + index length + version + magic.
+const FOOTER_SIZE: usize = 8 + 8 + 4 + 8;
+
+/// One record in the block index: the byte range of a data block and the first
+/// key it contains, used to binary-search for the block covering a lookup.
+struct IndexEntry {
+    offset: usize,
+    len: usize,
+    first_key: Bytes,
+}
+
+/// A read-only, memory-mapped SSTable.
+///
+/// The file is `mmap`ed once on [`open`](DiskTable::open) and never copied into
+/// heap buffers again. Point lookups binary-search the block index and
+/// then scan forward from there; values are returned as [`Bytes`] slices that
+/// borrow directly from the mapped region via [`Bytes::from_owner`].
+///
+/// # Safety
+///
+/// Every returned [`Bytes`] shares ownership of the underlying [`Mmap`], so the
+/// mapping is kept alive for at least as long as any slice handed out. Callers
+/// must not keep a returned [`Bytes`] past a point where the backing file is
+/// unlinked and the pages are reclaimed by the OS.
+pub struct DiskTable {
+    /// The whole file as a zero-copy `Bytes`; slices of it share the mapping.
+    data: Bytes,
+    index: Vec<IndexEntry>,
+}
+
+/// Wraps an [`Mmap`] so [`Bytes::from_owner`] can take ownership of it.
+struct MmapOwner(Mmap);
+
+impl AsRef<[u8]> for MmapOwner {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DiskTable {
+    /// Maps `path` read-only and parses its footer and block index. The data
+    /// blocks themselves stay on disk until a lookup faults them in.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the file is opened read-only and this process holds the only
+        // handle to the mapping; the pages back the `Bytes` we hand out.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let data = Bytes::from_owner(MmapOwner(mmap));
+
+        let total = data.len();
+        ensure!(total >= FOOTER_SIZE, "file too small to hold a footer");
+
+        let footer = &data[total - FOOTER_SIZE..];
+        let index_offset = u64::from_le_bytes(footer[0..8].try_into().unwrap()) as usize;
+        let index_len = u64::from_le_bytes(footer[8..16].try_into().unwrap()) as usize;
+        let version = u32::from_le_bytes(footer[16..20].try_into().unwrap());
+        let magic = u64::from_le_bytes(footer[20..28].try_into().unwrap());
+
+        ensure!(magic == MAGIC, "not a boulder SSTable (bad magic)");
+        ensure!(version == FORMAT_VERSION, "unsupported SSTable version {version}");
+
+        let data_end = total - FOOTER_SIZE;
+        let index_end = index_offset
+            .checked_add(index_len)
+            .filter(|&end| end <= data_end)
+            .ok_or_else(|| anyhow!("block index runs past the footer"))?;
+
+        let index = Self::parse_index(&data[index_offset..index_end])?;
+        Ok(DiskTable { data, index })
+    }
+
+    /// Decodes the block index: a sequence of `(offset u64, len u32,
+    /// first_key_len u32, first_key bytes)` records.
+    fn parse_index(mut buf: &[u8]) -> Result<Vec<IndexEntry>> {
+        let mut index = Vec::new();
+        while !buf.is_empty() {
+            ensure!(buf.len() >= 16, "truncated index record");
+            let offset = u64::from_le_bytes(buf[0..8].try_into().unwrap()) as usize;
+            let len = u32::from_le_bytes(buf[8..12].try_into().unwrap()) as usize;
+            let key_len = u32::from_le_bytes(buf[12..16].try_into().unwrap()) as usize;
+            ensure!(buf.len() >= 16 + key_len, "truncated index key");
+            let first_key = Bytes::copy_from_slice(&buf[16..16 + key_len]);
+            index.push(IndexEntry { offset, len, first_key });
+            buf = &buf[16 + key_len..];
+        }
+        Ok(index)
+    }
+
+    /// Returns the value of the newest visible version of `user_key`, or `None`
+    /// if the key is absent or its newest version is a tombstone. The returned
+    /// [`Bytes`] borrows from the mapping without copying.
+    pub fn get(&self, user_key: &[u8]) -> Result<Option<Bytes>> {
+        let Some(block) = self.seek_block(user_key) else {
+            return Ok(None);
+        };
+        // Start at the candidate block and scan forward: a key's versions may
+        // straddle a block boundary, so we must continue into adjacent blocks
+        // until we pass `user_key`.
+        let mut iter = BlockIter::new(self.data.clone(), block.offset, self.block_region_end())?;
+        let mut newest: Option<(KeyTrailer, Bytes)> = None;
+        while !iter.key().is_empty() {
+            let key = iter.key();
+            if key.key_ref() == user_key {
+                let ts: u64 = key.timestamp();
+                if newest.as_ref().map_or(true, |(t, _)| {
+                    let seen: u64 = (*t).into();
+                    ts >= seen
+                }) {
+                    newest = Some((key.trailer(), iter.value_bytes()));
+                }
+            } else if key.key_ref() > user_key {
+                break;
+            }
+            iter.next()?;
+        }
+        Ok(match newest {
+            Some((trailer, value)) => match trailer.into() {
+                KeyKind::Set => Some(value),
+                KeyKind::Delete => None,
+            },
+            None => None,
+        })
+    }
+
+    /// Returns a scan over every entry in the table in key order.
+    pub fn scan(&self) -> Result<BlockIter> {
+        let region_end = self.block_region_end();
+        // The data blocks occupy everything before the index; the first index
+        // entry marks the start of the block region.
+        let start = self.index.first().map(|e| e.offset).unwrap_or(region_end);
+        BlockIter::new(self.data.clone(), start, region_end)
+    }
+
+    /// Byte offset where the data blocks end and the index begins.
+    fn block_region_end(&self) -> usize {
+        self.index
+            .iter()
+            .map(|e| e.offset + e.len)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Binary-searches the index for the block that may contain `user_key`.
+    fn seek_block(&self, user_key: &[u8]) -> Option<&IndexEntry> {
+        if self.index.is_empty() {
+            return None;
+        }
+        // Find a block whose first key equals `user_key` (any one of them when
+        // several do), or else the last block whose first key is below it.
+        let idx = match self
+            .index
+            .binary_search_by(|e| e.first_key.as_ref().cmp(user_key))
+        {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        self.index.get(idx)
+    }
+}
+
+/// A forward cursor over the entries of a contiguous run of mapped data blocks,
+/// from a block start to the end of the block region. It borrows from the
+/// shared mapping, so advancing it never copies key or value bytes.
+pub struct BlockIter {
+    data: Bytes,
+    end: usize,
+    pos: usize,
+    key_range: (usize, usize),
+    trailer: KeyTrailer,
+    value_range: (usize, usize),
+}
+
+impl BlockIter {
+    fn new(data: Bytes, start: usize, end: usize) -> Result<Self> {
+        let mut iter = BlockIter {
+            data,
+            end,
+            pos: start,
+            key_range: (start, start),
+            trailer: KeyTrailer::from_raw(0),
+            value_range: (start, start),
+        };
+        iter.decode()?;
+        Ok(iter)
+    }
+
+    /// Decodes the entry at `pos`, or leaves an empty key when the range ends.
+    /// Every field is bounds-checked against `end` so a truncated or corrupt
+    /// mapping surfaces an error instead of panicking on an out-of-range index.
+    fn decode(&mut self) -> Result<()> {
+        if self.pos >= self.end {
+            self.key_range = (self.pos, self.pos);
+            self.value_range = (self.pos, self.pos);
+            return Ok(());
+        }
+        let buf = &self.data[..];
+        let end = self.end;
+
+        ensure!(self.pos + 4 <= end, "truncated entry key length");
+        let key_len = u32::from_le_bytes(buf[self.pos..self.pos + 4].try_into().unwrap()) as usize;
+        let key_start = self.pos + 4;
+        let key_end = key_start
+            .checked_add(key_len)
+            .filter(|&e| e <= end)
+            .ok_or_else(|| anyhow!("truncated entry key"))?;
+        ensure!(
+            key_end.checked_add(12).is_some_and(|e| e <= end),
+            "truncated entry header",
+        );
+        let raw = u64::from_le_bytes(buf[key_end..key_end + 8].try_into().unwrap());
+        let value_len =
+            u32::from_le_bytes(buf[key_end + 8..key_end + 12].try_into().unwrap()) as usize;
+        let value_start = key_end + 12;
+        let value_end = value_start
+            .checked_add(value_len)
+            .filter(|&e| e <= end)
+            .ok_or_else(|| anyhow!("truncated entry value"))?;
+
+        self.key_range = (key_start, key_end);
+        self.trailer = KeyTrailer::from_raw(raw);
+        self.value_range = (value_start, value_end);
+        self.pos = value_end;
+        Ok(())
+    }
+
+    /// The current value as a zero-copy slice of the mapping.
+    pub fn value_bytes(&self) -> Bytes {
+        self.data.slice(self.value_range.0..self.value_range.1)
+    }
+}
+
+impl TraitIterator for BlockIter {
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn value(&self) -> &[u8] {
+        &self.data[self.value_range.0..self.value_range.1]
+    }
+
+    fn key(&self) -> KeySlice<'_> {
+        KeySlice::from_trailer(&self.data[self.key_range.0..self.key_range.1], self.trailer)
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.decode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const SET: u64 = 1;
+    const DELETE: u64 = 0;
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    fn tmp_path() -> PathBuf {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("boulder-sstable-{}-{n}.sst", std::process::id()))
+    }
+
+    fn trailer_raw(ts: u64, kind: u64) -> u64 {
+        ts << 8 | kind
+    }
+
+    /// Serializes `blocks` (each a list of `(key, ts, kind, value)` entries,
+    /// globally sorted) into the on-disk SSTable layout the reader expects.
+    fn build(blocks: &[Vec<(&str, u64, u64, &str)>]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut index: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+        for block in blocks {
+            let offset = data.len();
+            let first_key = block[0].0.as_bytes().to_vec();
+            for &(key, ts, kind, value) in block {
+                data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                data.extend_from_slice(key.as_bytes());
+                data.extend_from_slice(&trailer_raw(ts, kind).to_le_bytes());
+                data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                data.extend_from_slice(value.as_bytes());
+            }
+            index.push((offset, data.len() - offset, first_key));
+        }
+
+        let index_offset = data.len();
+        let mut out = data;
+        for (offset, len, first_key) in &index {
+            out.extend_from_slice(&(*offset as u64).to_le_bytes());
+            out.extend_from_slice(&(*len as u32).to_le_bytes());
+            out.extend_from_slice(&(first_key.len() as u32).to_le_bytes());
+            out.extend_from_slice(first_key);
+        }
+        let index_len = out.len() - index_offset;
+
+        out.extend_from_slice(&(index_offset as u64).to_le_bytes());
+        out.extend_from_slice(&(index_len as u64).to_le_bytes());
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&MAGIC.to_le_bytes());
+        out
+    }
+
+    fn write_table(bytes: &[u8]) -> PathBuf {
+        let path = tmp_path();
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn get_reads_newest_version_across_block_boundary() {
+        // "m" fills two blocks both starting with "m"; the newest version lives
+        // in the second block and must be found even though `seek_block` may
+        // land on the first.
+        let bytes = build(&[
+            vec![("a", 1, SET, "va")],
+            vec![("m", 1, SET, "v1"), ("m", 2, SET, "v2")],
+            vec![("m", 3, SET, "v3"), ("m", 4, SET, "v4")],
+        ]);
+        let path = write_table(&bytes);
+        let table = DiskTable::open(&path).unwrap();
+
+        assert_eq!(table.get(b"m").unwrap(), Some(Bytes::from_static(b"v4")));
+        assert_eq!(table.get(b"a").unwrap(), Some(Bytes::from_static(b"va")));
+        assert_eq!(table.get(b"zzz").unwrap(), None);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn get_returns_none_for_newest_tombstone() {
+        let bytes = build(&[vec![("k", 1, SET, "v"), ("k", 2, DELETE, "")]]);
+        let path = write_table(&bytes);
+        let table = DiskTable::open(&path).unwrap();
+        assert_eq!(table.get(b"k").unwrap(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn open_rejects_bad_magic() {
+        let mut bytes = build(&[vec![("a", 1, SET, "va")]]);
+        let len = bytes.len();
+        // Corrupt the trailing magic.
+        bytes[len - 1] ^= 0xFF;
+        let path = write_table(&bytes);
+        assert!(DiskTable::open(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn open_rejects_out_of_range_index_offset() {
+        let mut bytes = build(&[vec![("a", 1, SET, "va")]]);
+        let len = bytes.len();
+        // Overwrite index_offset (first 8 bytes of the footer) with a huge value
+        // so the checked arithmetic rejects it instead of panicking.
+        let footer = len - FOOTER_SIZE;
+        bytes[footer..footer + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let path = write_table(&bytes);
+        assert!(DiskTable::open(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn open_rejects_undersized_file() {
+        let path = write_table(&[0u8; 4]);
+        assert!(DiskTable::open(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,3 +1,11 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::key::{KeyBytes, KeyKind, KeySlice, KeyTimestamp};
+
 pub trait TraitIterator {
     type KeyType<'a>: PartialEq + Eq + PartialOrd + Ord
     where
@@ -12,3 +20,263 @@ pub trait TraitIterator {
     /// Move to the next position.
     fn next(&mut self) -> anyhow::Result<()>;
 }
+
+/// A snapshot of one child iterator's position, buffered on the merge heap so
+/// the child can be advanced while the entry waits its turn. Shared by every
+/// consumer of [`SourceHeap`] (snapshot reads and compaction alike) so the
+/// ordering and advance logic can never drift between them.
+pub(crate) struct HeapEntry {
+    pub(crate) key: KeyBytes,
+    pub(crate) value: Bytes,
+    source: usize,
+}
+
+impl HeapEntry {
+    pub(crate) fn timestamp(&self) -> KeyTimestamp {
+        self.key.timestamp()
+    }
+
+    pub(crate) fn kind(&self) -> KeyKind {
+        self.key.kind()
+    }
+
+    pub(crate) fn user_key(&self) -> &[u8] {
+        self.key.key_ref()
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    /// Orders `(user_key asc, timestamp desc, source asc)` so the newest
+    /// version of the smallest key sorts first, and a newer source (lower
+    /// index) shadows an older one at an equal `(user_key, timestamp)`.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .key_ref()
+            .cmp(other.key.key_ref())
+            .then_with(|| other.timestamp().cmp(&self.timestamp()))
+            .then_with(|| self.source.cmp(&other.source))
+    }
+}
+
+/// A min-heap over N child iterators that orders the current head of each
+/// source by `(user_key asc, timestamp desc, source asc)`. Popping an entry
+/// transparently advances the source it came from and re-inserts that source's
+/// next position, so callers only ever see the smallest pending head. User keys
+/// come out in ascending order, but one source's versions of a user key come
+/// out in that source's own order, so consumers sort each key's versions.
+///
+/// Both the snapshot [`MergeIterator`] and the compaction pass build on this,
+/// so fixes to the ordering or advance logic apply to both at once.
+pub(crate) struct SourceHeap<I> {
+    sources: Vec<I>,
+    heap: BinaryHeap<Reverse<HeapEntry>>,
+}
+
+impl<I> SourceHeap<I>
+where
+    I: for<'a> TraitIterator<KeyType<'a> = KeySlice<'a>> + 'static,
+{
+    /// Builds a heap seeded with the current head of every source. Sources must
+    /// be listed newest-first so ties break in favour of fresher data.
+    pub(crate) fn new(sources: Vec<I>) -> Self {
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        for (idx, source) in sources.iter().enumerate() {
+            if let Some(entry) = Self::head(idx, source) {
+                heap.push(Reverse(entry));
+            }
+        }
+        SourceHeap { sources, heap }
+    }
+
+    /// Snapshots the current position of `source`, or `None` if it is drained.
+    fn head(idx: usize, source: &I) -> Option<HeapEntry> {
+        let key = source.key();
+        if key.is_empty() {
+            return None;
+        }
+        Some(HeapEntry {
+            key: key.to_key_vec().into_key_bytes(),
+            value: Bytes::copy_from_slice(source.value()),
+            source: idx,
+        })
+    }
+
+    /// The smallest pending entry without consuming it.
+    pub(crate) fn peek(&self) -> Option<&HeapEntry> {
+        self.heap.peek().map(|Reverse(entry)| entry)
+    }
+
+    /// Pops the smallest pending entry, advancing and re-inserting the source it
+    /// came from.
+    pub(crate) fn pop(&mut self) -> Result<Option<HeapEntry>> {
+        let Some(Reverse(entry)) = self.heap.pop() else {
+            return Ok(None);
+        };
+        let source = entry.source;
+        self.sources[source].next()?;
+        if let Some(next) = Self::head(source, &self.sources[source]) {
+            self.heap.push(Reverse(next));
+        }
+        Ok(Some(entry))
+    }
+}
+
+/// A single snapshot-consistent scan over every source of the LSM — the
+/// memtable and the sorted on-disk runs — merged into one key-ordered stream.
+///
+/// Children yield their entries in key order; because a user key recurs at many
+/// timestamps, that key's versions are gathered off the per-source heap and
+/// ordered `(timestamp desc, source asc)` so the newest version is visited
+/// first. For each distinct user key the
+/// iterator surfaces only the newest version whose timestamp is
+/// `<= read_timestamp`, skips that key's remaining versions, and suppresses the
+/// key entirely when the chosen version is a [`KeyKind::Delete`] tombstone.
+/// Sources are listed newest-first so a freshly written value shadows an
+/// equal-timestamp stale value from an older run.
+pub struct MergeIterator<I> {
+    heap: SourceHeap<I>,
+    read_timestamp: KeyTimestamp,
+    current_key: KeyBytes,
+    current_value: Bytes,
+}
+
+impl<I> MergeIterator<I>
+where
+    I: for<'a> TraitIterator<KeyType<'a> = KeySlice<'a>> + 'static,
+{
+    /// Merges `sources` (newest first) into one stream visible as of
+    /// `read_timestamp`.
+    pub fn new(sources: Vec<I>, read_timestamp: KeyTimestamp) -> Result<Self> {
+        let mut iter = MergeIterator {
+            heap: SourceHeap::new(sources),
+            read_timestamp,
+            current_key: KeyBytes::new(),
+            current_value: Bytes::new(),
+        };
+        iter.advance()?;
+        Ok(iter)
+    }
+
+    /// Steps to the next distinct, visible, non-deleted user key, or leaves the
+    /// iterator positioned on an empty key once the merge is exhausted.
+    fn advance(&mut self) -> Result<()> {
+        loop {
+            let Some(first) = self.heap.pop()? else {
+                self.current_key = KeyBytes::new();
+                self.current_value = Bytes::new();
+                return Ok(());
+            };
+            let user_key = first.user_key().to_vec();
+
+            // Drain the remaining versions of this user key.
+            let mut group = vec![first];
+            while self
+                .heap
+                .peek()
+                .is_some_and(|entry| entry.user_key() == user_key.as_slice())
+            {
+                group.push(self.heap.pop()?.unwrap());
+            }
+            // Sorted newest-first, the first version at or below the snapshot is
+            // the version the reader should see.
+            group.sort();
+            let chosen = group
+                .into_iter()
+                .find(|entry| entry.timestamp() <= self.read_timestamp);
+
+            match chosen {
+                Some(entry) if matches!(entry.kind(), KeyKind::Set) => {
+                    self.current_key = entry.key;
+                    self.current_value = entry.value;
+                    return Ok(());
+                }
+                // No version visible at the snapshot, or the visible version is a
+                // tombstone: skip this user key and try the next one.
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl<I> TraitIterator for MergeIterator<I>
+where
+    I: for<'a> TraitIterator<KeyType<'a> = KeySlice<'a>> + 'static,
+{
+    type KeyType<'a>
+        = KeySlice<'a>
+    where
+        Self: 'a;
+
+    fn value(&self) -> &[u8] {
+        &self.current_value
+    }
+
+    fn key(&self) -> KeySlice<'_> {
+        KeySlice::from_trailer(self.current_key.key_ref(), self.current_key.trailer())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.advance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{entry, VecIter};
+
+    fn collect(mut iter: MergeIterator<VecIter>) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut out = Vec::new();
+        while !iter.key().is_empty() {
+            out.push((iter.key().key_ref().to_vec(), iter.value().to_vec()));
+            iter.next().unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn newest_source_wins_tie() {
+        // Same user key and timestamp in two sources; the newer source (listed
+        // first) must shadow the stale value.
+        let newer = VecIter::new(vec![entry("a", 1, KeyKind::Set, "new")]);
+        let older = VecIter::new(vec![entry("a", 1, KeyKind::Set, "old")]);
+        let out = collect(MergeIterator::new(vec![newer, older], 10).unwrap());
+        assert_eq!(out, vec![(b"a".to_vec(), b"new".to_vec())]);
+    }
+
+    #[test]
+    fn suppresses_tombstone_at_snapshot() {
+        let source = VecIter::new(vec![
+            entry("a", 1, KeyKind::Set, "v"),
+            entry("a", 2, KeyKind::Delete, ""),
+        ]);
+        // As of ts 5 the newest visible version is the tombstone: key is hidden.
+        let out = collect(MergeIterator::new(vec![source], 5).unwrap());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn reads_version_visible_at_timestamp() {
+        let source = VecIter::new(vec![
+            entry("a", 1, KeyKind::Set, "past"),
+            entry("a", 5, KeyKind::Set, "future"),
+        ]);
+        // A read at ts 3 must not see the write committed at ts 5.
+        let out = collect(MergeIterator::new(vec![source], 3).unwrap());
+        assert_eq!(out, vec![(b"a".to_vec(), b"past".to_vec())]);
+    }
+}
@@ -37,6 +37,11 @@ impl KeyTrailer {
         KeyTrailer(ts << 8 | kind as u64)
     }
 
+    /// Rebuilds a trailer from its packed on-disk representation.
+    pub fn from_raw(raw: u64) -> Self {
+        KeyTrailer(raw)
+    }
+
     fn kind(&self) -> KeyKind {
         KeyKind::try_from((self.0 & 0xff) as u8).unwrap()
     }
@@ -65,6 +70,12 @@ pub type KeyVec = Key<Vec<u8>>;
 pub type KeyBytes = Key<Bytes>;
 
 impl<T: AsRef<[u8]>> Key<T> {
+    /// Assembles a key from a backing buffer and its decoded trailer. Used when
+    /// reconstructing keys off disk so the MVCC trailer round-trips intact.
+    pub fn from_trailer(key: T, trailer: KeyTrailer) -> Self {
+        Key(key, trailer)
+    }
+
     pub fn into_inner(self) -> T {
         self.0
     }
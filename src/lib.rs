@@ -11,5 +11,7 @@ mod iterator;
 mod key;
 mod manifest;
 mod mem_table;
+#[cfg(test)]
+mod test_util;
 mod transaction;
 mod wal;
@@ -0,0 +1,352 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{bail, ensure, Result};
+
+use crate::key::KeyTimestamp;
+
+/// Identifies a boulder manifest and rejects foreign files.
+const MAGIC: u64 = 0x424f554c_4d414e49; // "BOULMANI"
+/// Header length: magic + format_version + min_reader_version.
+const HEADER_LEN: u64 = 8 + 2 + 2;
+
+/// The manifest format this code writes.
+const FORMAT_VERSION: u16 = 1;
+/// The lowest reader version able to read manifests this code writes, recorded
+/// in every header so an old binary can refuse a file it could not interpret.
+const MIN_READER_VERSION: u16 = 1;
+/// The manifest format this code is able to read. A file whose
+/// `min_reader_version` exceeds this is rejected on open.
+const READER_VERSION: u16 = 1;
+
+// Edit-record tags. New tags may be appended by newer writers; an older reader
+// ignores tags it does not recognise.
+const TAG_ADD_TABLE: u8 = 1;
+const TAG_DELETE_TABLE: u8 = 2;
+const TAG_ADVANCE_SEQUENCE: u8 = 3;
+const TAG_SET_WATERMARK: u8 = 4;
+
+/// A single mutation of the manifest's logical state. Records are
+/// self-describing and length-delimited, so a newer writer can append trailing
+/// fields an older reader simply ignores.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Edit {
+    /// A new SSTable became live at a level.
+    AddTable { id: u64, level: u32, file_size: u64 },
+    /// An SSTable was removed (compacted away).
+    DeleteTable { id: u64 },
+    /// The next sequence number advanced.
+    AdvanceSequence(KeyTimestamp),
+    /// The MVCC garbage-collection watermark advanced.
+    SetWatermark(KeyTimestamp),
+}
+
+/// Metadata for one live SSTable.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TableInfo {
+    pub id: u64,
+    pub level: u32,
+    pub file_size: u64,
+}
+
+/// The in-memory state reconstructed by replaying the manifest log.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ManifestState {
+    /// Live tables keyed by id.
+    pub tables: BTreeMap<u64, TableInfo>,
+    /// The next sequence number to hand out.
+    pub next_sequence: KeyTimestamp,
+    /// The GC watermark below which versions have been collapsed.
+    pub gc_watermark: KeyTimestamp,
+}
+
+impl ManifestState {
+    fn apply(&mut self, edit: Edit) {
+        match edit {
+            Edit::AddTable { id, level, file_size } => {
+                self.tables.insert(id, TableInfo { id, level, file_size });
+            }
+            Edit::DeleteTable { id } => {
+                self.tables.remove(&id);
+            }
+            Edit::AdvanceSequence(seq) => self.next_sequence = seq,
+            Edit::SetWatermark(ts) => self.gc_watermark = ts,
+        }
+    }
+}
+
+/// An append-only, CRC-checked manifest log.
+///
+/// The file opens with a header carrying [`MAGIC`], a `format_version`, and a
+/// `min_reader_version`. A reader refuses a manifest whose
+/// `min_reader_version` is above its own [`READER_VERSION`], but tolerates a
+/// higher `format_version` and unknown trailing fields so non-breaking
+/// additions do not lock out older code. Each edit is framed with its length
+/// and a CRC32 so a torn final write is detected and truncated on recovery.
+pub struct Manifest {
+    file: Mutex<File>,
+}
+
+impl Manifest {
+    /// Opens (creating if absent) the manifest at `path`, replays every intact
+    /// edit record to reconstruct the live table set, and truncates any torn
+    /// trailing record so subsequent appends start from a clean boundary.
+    pub fn replay(path: impl AsRef<Path>) -> Result<(Manifest, ManifestState)> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
+
+        let file_len = file.metadata()?.len();
+        if file_len == 0 {
+            write_header(&mut file)?;
+            return Ok((Manifest { file: Mutex::new(file) }, ManifestState::default()));
+        }
+
+        read_header(&mut file)?;
+
+        let mut state = ManifestState::default();
+        let mut offset = HEADER_LEN;
+        loop {
+            let Some((edit, record_len)) = read_record(&mut file, offset, file_len)? else {
+                // Either a clean end of log or a torn final write: truncate to
+                // the last good boundary so the next append is well-formed.
+                file.set_len(offset)?;
+                break;
+            };
+            if let Some(edit) = edit {
+                state.apply(edit);
+            }
+            offset += record_len;
+        }
+
+        file.seek(SeekFrom::End(0))?;
+        Ok((Manifest { file: Mutex::new(file) }, state))
+    }
+
+    /// Atomically appends `edit` and flushes it to stable storage.
+    pub fn append(&self, edit: &Edit) -> Result<()> {
+        let payload = encode_edit(edit);
+        let crc = crc32fast::hash(&payload);
+
+        let mut framed = Vec::with_capacity(8 + payload.len());
+        framed.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        framed.extend_from_slice(&crc.to_le_bytes());
+        framed.extend_from_slice(&payload);
+
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(&framed)?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+fn write_header(file: &mut File) -> Result<()> {
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    header.extend_from_slice(&MAGIC.to_le_bytes());
+    header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    header.extend_from_slice(&MIN_READER_VERSION.to_le_bytes());
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&header)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+fn read_header(file: &mut File) -> Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut buf = [0u8; HEADER_LEN as usize];
+    file.read_exact(&mut buf)?;
+    let magic = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+    let _format_version = u16::from_le_bytes(buf[8..10].try_into().unwrap());
+    let min_reader_version = u16::from_le_bytes(buf[10..12].try_into().unwrap());
+
+    ensure!(magic == MAGIC, "not a boulder manifest (bad magic)");
+    if READER_VERSION < min_reader_version {
+        bail!(
+            "manifest requires reader version {min_reader_version}, but this build is {READER_VERSION}",
+        );
+    }
+    Ok(())
+}
+
+/// Reads the record at `offset`. Returns `None` when the log ends cleanly or the
+/// trailing record is torn (short read or CRC mismatch); otherwise returns the
+/// decoded edit (or `None` edit for an unknown, forward-compatible tag) and the
+/// total on-disk length of the record.
+fn read_record(
+    file: &mut File,
+    offset: u64,
+    file_len: u64,
+) -> Result<Option<(Option<Edit>, u64)>> {
+    if offset + 8 > file_len {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::Start(offset))?;
+    let mut frame = [0u8; 8];
+    file.read_exact(&mut frame)?;
+    let payload_len = u32::from_le_bytes(frame[0..4].try_into().unwrap()) as u64;
+    let expected_crc = u32::from_le_bytes(frame[4..8].try_into().unwrap());
+
+    if offset + 8 + payload_len > file_len {
+        // The final record never finished writing.
+        return Ok(None);
+    }
+
+    let mut payload = vec![0u8; payload_len as usize];
+    file.read_exact(&mut payload)?;
+    if crc32fast::hash(&payload) != expected_crc {
+        return Ok(None);
+    }
+
+    Ok(Some((decode_edit(&payload), 8 + payload_len)))
+}
+
+fn encode_edit(edit: &Edit) -> Vec<u8> {
+    let mut buf = Vec::new();
+    match *edit {
+        Edit::AddTable { id, level, file_size } => {
+            buf.push(TAG_ADD_TABLE);
+            buf.extend_from_slice(&id.to_le_bytes());
+            buf.extend_from_slice(&level.to_le_bytes());
+            buf.extend_from_slice(&file_size.to_le_bytes());
+        }
+        Edit::DeleteTable { id } => {
+            buf.push(TAG_DELETE_TABLE);
+            buf.extend_from_slice(&id.to_le_bytes());
+        }
+        Edit::AdvanceSequence(seq) => {
+            buf.push(TAG_ADVANCE_SEQUENCE);
+            buf.extend_from_slice(&seq.to_le_bytes());
+        }
+        Edit::SetWatermark(ts) => {
+            buf.push(TAG_SET_WATERMARK);
+            buf.extend_from_slice(&ts.to_le_bytes());
+        }
+    }
+    buf
+}
+
+/// Decodes an edit payload, reading only the fields this version knows about and
+/// ignoring any trailing bytes a newer writer may have appended. An unrecognised
+/// tag yields `None` so the record is skipped rather than rejected.
+fn decode_edit(payload: &[u8]) -> Option<Edit> {
+    let (&tag, rest) = payload.split_first()?;
+    match tag {
+        TAG_ADD_TABLE => Some(Edit::AddTable {
+            id: read_u64(rest, 0)?,
+            level: read_u32(rest, 8)?,
+            file_size: read_u64(rest, 12)?,
+        }),
+        TAG_DELETE_TABLE => Some(Edit::DeleteTable {
+            id: read_u64(rest, 0)?,
+        }),
+        TAG_ADVANCE_SEQUENCE => Some(Edit::AdvanceSequence(read_u64(rest, 0)?)),
+        TAG_SET_WATERMARK => Some(Edit::SetWatermark(read_u64(rest, 0)?)),
+        _ => None,
+    }
+}
+
+fn read_u64(buf: &[u8], at: usize) -> Option<u64> {
+    buf.get(at..at + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_u32(buf: &[u8], at: usize) -> Option<u32> {
+    buf.get(at..at + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    fn tmp_path() -> PathBuf {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("boulder-manifest-{}-{n}.log", std::process::id()))
+    }
+
+    #[test]
+    fn replays_edits_into_state() {
+        let path = tmp_path();
+        {
+            let (manifest, state) = Manifest::replay(&path).unwrap();
+            assert_eq!(state, ManifestState::default());
+            manifest
+                .append(&Edit::AddTable { id: 1, level: 0, file_size: 100 })
+                .unwrap();
+            manifest.append(&Edit::AdvanceSequence(42)).unwrap();
+            manifest.append(&Edit::SetWatermark(7)).unwrap();
+            manifest
+                .append(&Edit::AddTable { id: 2, level: 1, file_size: 200 })
+                .unwrap();
+            manifest.append(&Edit::DeleteTable { id: 1 }).unwrap();
+        }
+
+        let (_manifest, state) = Manifest::replay(&path).unwrap();
+        assert_eq!(state.next_sequence, 42);
+        assert_eq!(state.gc_watermark, 7);
+        assert_eq!(state.tables.len(), 1);
+        assert!(!state.tables.contains_key(&1));
+        assert_eq!(
+            state.tables.get(&2),
+            Some(&TableInfo { id: 2, level: 1, file_size: 200 }),
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn truncates_corrupt_trailing_record() {
+        let path = tmp_path();
+        {
+            let (manifest, _) = Manifest::replay(&path).unwrap();
+            manifest.append(&Edit::AdvanceSequence(10)).unwrap();
+            manifest.append(&Edit::AdvanceSequence(20)).unwrap();
+        }
+        let original_len = std::fs::metadata(&path).unwrap().len();
+
+        // Corrupt the final byte so the last record fails its CRC check.
+        {
+            let mut file = OpenOptions::new().read(true).write(true).open(&path).unwrap();
+            file.seek(SeekFrom::End(-1)).unwrap();
+            let mut last = [0u8; 1];
+            file.read_exact(&mut last).unwrap();
+            file.seek(SeekFrom::End(-1)).unwrap();
+            file.write_all(&[last[0] ^ 0xFF]).unwrap();
+        }
+
+        let (_manifest, state) = Manifest::replay(&path).unwrap();
+        // Only the first, intact record survives.
+        assert_eq!(state.next_sequence, 10);
+        // The torn record was truncated off the log.
+        assert!(std::fs::metadata(&path).unwrap().len() < original_len);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn unknown_tag_is_skipped() {
+        // A record carrying a tag this reader does not know must be ignored
+        // rather than rejected, so newer writers can add edits safely.
+        assert!(decode_edit(&[250, 1, 2, 3]).is_none());
+        assert_eq!(
+            decode_edit(&{
+                let mut p = vec![TAG_SET_WATERMARK];
+                p.extend_from_slice(&9u64.to_le_bytes());
+                // Trailing bytes from a hypothetical newer writer are ignored.
+                p.extend_from_slice(&[0xAA, 0xBB]);
+                p
+            }),
+            Some(Edit::SetWatermark(9)),
+        );
+    }
+}
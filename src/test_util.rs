@@ -0,0 +1,47 @@
+use anyhow::Result;
+
+use crate::iterator::TraitIterator;
+use crate::key::{KeyKind, KeySlice, KeyTimestamp, KeyTrailer};
+
+/// A test iterator that replays a fixed, pre-sorted list of entries and
+/// signals exhaustion with an empty key.
+pub(crate) struct VecIter {
+    items: Vec<(Vec<u8>, KeyTrailer, Vec<u8>)>,
+    pos: usize,
+}
+
+impl VecIter {
+    pub(crate) fn new(items: Vec<(Vec<u8>, KeyTrailer, Vec<u8>)>) -> Self {
+        VecIter { items, pos: 0 }
+    }
+}
+
+impl TraitIterator for VecIter {
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn value(&self) -> &[u8] {
+        self.items.get(self.pos).map(|e| e.2.as_slice()).unwrap_or_default()
+    }
+
+    fn key(&self) -> KeySlice<'_> {
+        match self.items.get(self.pos) {
+            Some(e) => KeySlice::from_trailer(e.0.as_slice(), e.1),
+            None => KeySlice::from_trailer(&[][..], KeyTrailer::new(0, KeyKind::Delete)),
+        }
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.pos += 1;
+        Ok(())
+    }
+}
+
+/// Builds one `(user_key, trailer, value)` entry for a [`VecIter`].
+pub(crate) fn entry(
+    key: &str,
+    ts: KeyTimestamp,
+    kind: KeyKind,
+    value: &str,
+) -> (Vec<u8>, KeyTrailer, Vec<u8>) {
+    (key.as_bytes().to_vec(), KeyTrailer::new(ts, kind), value.as_bytes().to_vec())
+}
@@ -1,7 +1,19 @@
-/// Transactions
-/// 
-/// Should be lazily executed, like async/await
-/// 
+//! Transactions
+//!
+//! Transactions are lazily executed, like async/await: [`DB::transaction`]
+//! captures a closure and nothing runs until [`TransactionHandle::execute`]
+//! drives it. A transaction pins a `read_timestamp` at begin time — the latest
+//! committed sequence number — and buffers its own writes locally, so it reads
+//! a stable snapshot regardless of concurrent commits.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::db::DbInner;
+use crate::key::KeyTimestamp;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Consistency {
@@ -9,23 +21,204 @@ pub enum Consistency {
     Synchronous,
 }
 
-pub struct TransactionHandle {
-    
+/// The outcome of driving a transaction to its commit point.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TransactionResult {
+    /// The transaction committed at the given sequence number.
+    Committed(KeyTimestamp),
+    /// Optimistic validation found a read that was overwritten by a newer
+    /// commit; the transaction was aborted and applied nothing.
+    Conflict,
+}
+
+/// A prepared but not-yet-executed transaction. It owns the body closure so
+/// the work is deferred until [`execute`](TransactionHandle::execute) selects a
+/// [`Consistency`] level and runs it.
+pub struct TransactionHandle<F> {
+    inner: Arc<DbInner>,
+    body: Option<F>,
 }
 
-impl TransactionHandle {
-    pub fn execute(&self, consistency: Consistency) {
-        unimplemented!()
+impl<F> TransactionHandle<F>
+where
+    F: FnOnce(&mut Transaction) -> Result<()>,
+{
+    pub(crate) fn new(inner: Arc<DbInner>, body: F) -> Self {
+        TransactionHandle {
+            inner,
+            body: Some(body),
+        }
+    }
+
+    /// Runs the transaction body under the requested consistency and commits.
+    ///
+    /// Under [`Consistency::Optimistic`] the body runs without holding the
+    /// commit lock; at commit the read set is validated against newer commits
+    /// and a conflicting transaction aborts with [`TransactionResult::Conflict`].
+    /// Under [`Consistency::Synchronous`] the commit lock is held for the whole
+    /// body, so it serializes without validation and can never conflict.
+    pub fn execute(&mut self, consistency: Consistency) -> Result<TransactionResult> {
+        let body = self.body.take().expect("transaction already executed");
+        match consistency {
+            Consistency::Optimistic => {
+                let mut txn = Transaction::begin(self.inner.clone(), consistency);
+                body(&mut txn)?;
+                Ok(self
+                    .inner
+                    .validate_and_commit(&txn.reads, &txn.writes, txn.read_timestamp))
+            }
+            Consistency::Synchronous => {
+                // Hold the commit lock across the whole body so it serializes
+                // with every other committer; no validation is needed.
+                let guard = self.inner.lock_commit();
+                let mut txn = Transaction::begin(self.inner.clone(), consistency);
+                body(&mut txn)?;
+                let sequence = self.inner.commit_locked(&txn.writes);
+                drop(guard);
+                Ok(TransactionResult::Committed(sequence))
+            }
+        }
     }
-    
-    pub fn default(&self) {
+
+    /// Executes with the default [`Consistency::Optimistic`] level.
+    pub fn default(&mut self) -> Result<TransactionResult> {
         self.execute(Consistency::Optimistic)
     }
 }
 
+/// A snapshot-isolated transaction. Reads observe the database as of
+/// `read_timestamp`; writes are buffered in `writes` and only become visible
+/// when the transaction commits.
 pub struct Transaction {
-    
+    inner: Arc<DbInner>,
+    read_timestamp: KeyTimestamp,
+    #[allow(dead_code)]
+    consistency: Consistency,
+    /// Locally buffered writes: `None` is a tombstone.
+    writes: BTreeMap<Bytes, Option<Bytes>>,
+    /// Keys observed by reads, validated at commit under optimistic control.
+    reads: BTreeSet<Bytes>,
 }
 
 impl Transaction {
-}
\ No newline at end of file
+    fn begin(inner: Arc<DbInner>, consistency: Consistency) -> Self {
+        let read_timestamp = inner.read_timestamp();
+        Transaction {
+            inner,
+            read_timestamp,
+            consistency,
+            writes: BTreeMap::new(),
+            reads: BTreeSet::new(),
+        }
+    }
+
+    /// Reads `key` as of the transaction's snapshot. A buffered own-write is
+    /// returned directly; otherwise the key is recorded in the read set and
+    /// resolved against the database snapshot.
+    pub fn get<K>(&mut self, key: K) -> Option<Bytes>
+    where
+        K: AsRef<[u8]>,
+    {
+        let key = Bytes::copy_from_slice(key.as_ref());
+        if let Some(buffered) = self.writes.get(&key) {
+            // Own-writes reads are served locally and never validated, so they
+            // must not enter the read set or they would spuriously conflict.
+            return buffered.clone();
+        }
+        self.reads.insert(key.clone());
+        self.inner.read(&key, self.read_timestamp)
+    }
+
+    /// Buffers a write to be applied atomically at commit.
+    pub fn insert<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<Bytes>,
+        V: Into<Bytes>,
+    {
+        self.writes.insert(key.into(), Some(value.into()));
+    }
+
+    /// Buffers a tombstone to be applied atomically at commit.
+    pub fn remove<K>(&mut self, key: K)
+    where
+        K: Into<Bytes>,
+    {
+        self.writes.insert(key.into(), None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DB;
+
+    fn bytes(s: &str) -> Bytes {
+        Bytes::copy_from_slice(s.as_bytes())
+    }
+
+    #[test]
+    fn optimistic_commit_applies_writes() {
+        let db = DB::open();
+        db.insert(bytes("x"), bytes("0")).unwrap();
+
+        let mut handle = db.transaction(|txn| {
+            // Read a key without anyone else touching it, then write.
+            let _ = txn.get("x");
+            txn.insert("y", "1");
+            Ok(())
+        });
+
+        let result = handle.execute(Consistency::Optimistic).unwrap();
+        assert!(matches!(result, TransactionResult::Committed(_)));
+        assert_eq!(db.get(bytes("y")), Some(bytes("1")));
+    }
+
+    #[test]
+    fn optimistic_conflict_aborts() {
+        let db = DB::open();
+        db.insert(bytes("x"), bytes("0")).unwrap();
+
+        let mut handle = db.transaction(|txn| {
+            // Read `x`, then a concurrent committer overwrites it with a newer
+            // version, invalidating the read set.
+            let _ = txn.get("x");
+            db.insert(bytes("x"), bytes("1")).unwrap();
+            Ok(())
+        });
+
+        let result = handle.execute(Consistency::Optimistic).unwrap();
+        assert_eq!(result, TransactionResult::Conflict);
+        // The aborted transaction applied nothing beyond the interfering write.
+        assert_eq!(db.get(bytes("x")), Some(bytes("1")));
+    }
+
+    #[test]
+    fn transaction_reads_own_writes() {
+        let db = DB::open();
+        let mut handle = db.transaction(|txn| {
+            txn.insert("k", "v");
+            assert_eq!(txn.get("k"), Some(bytes("v")));
+            Ok(())
+        });
+        assert!(matches!(
+            handle.execute(Consistency::Optimistic).unwrap(),
+            TransactionResult::Committed(_),
+        ));
+    }
+
+    #[test]
+    fn synchronous_commit_never_conflicts() {
+        let db = DB::open();
+        db.insert(bytes("x"), bytes("0")).unwrap();
+        let mut handle = db.transaction(|txn| {
+            let _ = txn.get("x");
+            txn.insert("x", "1");
+            Ok(())
+        });
+        assert!(matches!(
+            handle.execute(Consistency::Synchronous).unwrap(),
+            TransactionResult::Committed(_),
+        ));
+        assert_eq!(db.get(bytes("x")), Some(bytes("1")));
+    }
+}